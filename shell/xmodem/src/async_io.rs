@@ -0,0 +1,430 @@
+//! Async XMODEM over `tokio`'s `AsyncRead`/`AsyncWrite`.
+//!
+//! This mirrors the blocking implementation in the crate root byte for byte —
+//! the same NAK/C handshake, per-byte reads, checksum/CRC verification and
+//! ACK/NAK responses — with every `read_byte`/`write_byte` turned into an
+//! `.await` point so the protocol can run inside an event loop without
+//! blocking a thread per transfer. It is only compiled with the `tokio`
+//! feature enabled.
+
+use std::io;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::progress::{self, Progress, ProgressFn};
+use crate::{
+    is_timeout, Checksum, Error, Result, Xmodem, crc16, ACK, CAN, CRC, CRC_HANDSHAKE_RETRIES,
+    DEFAULT_MAX_ERRORS, EOT, NAK, SOH, STX,
+};
+
+/// Builds an async `Xmodem` over `inner`. The blocking `new`/`new_with_progress`
+/// require `std::io` bounds, so the async entry points construct directly.
+fn with_progress<T>(inner: T, f: ProgressFn) -> Xmodem<T> {
+    Xmodem { packet: 1, started: false, checksum: Checksum::Standard, max_errors: DEFAULT_MAX_ERRORS, errors: 0, block_1k: false, timeout: None, inner, progress: f }
+}
+
+impl Xmodem<()> {
+    /// Transmits `data` to the receiver `to` using the XMODEM protocol over an
+    /// async transport. See [`Xmodem::transmit`] for the blocking equivalent.
+    #[inline]
+    pub async fn transmit_async<R, W>(data: R, to: W) -> Result<usize>
+        where W: AsyncRead + AsyncWrite + Unpin, R: AsyncRead + Unpin
+    {
+        Xmodem::transmit_with_progress_async(data, to, progress::noop).await
+    }
+
+    /// Transmits `data` to the receiver `to` using the XMODEM protocol over an
+    /// async transport, reporting progress through `f`. See
+    /// [`Xmodem::transmit_with_progress`] for the blocking equivalent.
+    pub async fn transmit_with_progress_async<R, W>(data: R, to: W, f: ProgressFn) -> Result<usize>
+        where W: AsyncRead + AsyncWrite + Unpin, R: AsyncRead + Unpin
+    {
+        with_progress(to, f).transmit_stream(data).await
+    }
+
+    /// Transmits `data` using XMODEM-1K over an async transport. See the
+    /// blocking [`Xmodem::transmit_1k`] for the semantics.
+    #[inline]
+    pub async fn transmit_1k_async<R, W>(data: R, to: W) -> Result<usize>
+        where W: AsyncRead + AsyncWrite + Unpin, R: AsyncRead + Unpin
+    {
+        Xmodem::transmit_1k_with_progress_async(data, to, progress::noop).await
+    }
+
+    /// As [`transmit_1k_async`](Xmodem::transmit_1k_async), reporting progress
+    /// through `f`.
+    pub async fn transmit_1k_with_progress_async<R, W>(data: R, to: W, f: ProgressFn) -> Result<usize>
+        where W: AsyncRead + AsyncWrite + Unpin, R: AsyncRead + Unpin
+    {
+        let mut transmitter = with_progress(to, f);
+        transmitter.block_1k = true;
+        transmitter.transmit_stream(data).await
+    }
+
+    /// Receives `data` from `from` using the XMODEM protocol over an async
+    /// transport. See [`Xmodem::receive`] for the blocking equivalent.
+    #[inline]
+    pub async fn receive_async<R, W>(from: R, into: W) -> Result<usize>
+       where R: AsyncRead + AsyncWrite + Unpin, W: AsyncWrite + Unpin
+    {
+        Xmodem::receive_with_progress_async(from, into, progress::noop).await
+    }
+
+    /// Receives `data` from `from` using the XMODEM protocol over an async
+    /// transport, reporting progress through `f`. See
+    /// [`Xmodem::receive_with_progress`] for the blocking equivalent.
+    pub async fn receive_with_progress_async<R, W>(from: R, mut into: W, f: ProgressFn) -> Result<usize>
+       where R: AsyncRead + AsyncWrite + Unpin, W: AsyncWrite + Unpin
+    {
+        let mut receiver = with_progress(from, f);
+        let mut packet = [0u8; 1024];
+        let mut received = 0;
+        'next_packet: loop {
+            loop {
+                match receiver.read_packet(&mut packet).await {
+                    Err(Error::ChecksumMismatch) => continue,
+                    Err(e) => return Err(e),
+                    Ok(0) => break 'next_packet,
+                    Ok(n) => {
+                        received += n;
+                        into.write_all(&packet[..n]).await?;
+                        continue 'next_packet;
+                    }
+                }
+            }
+        }
+
+        Ok(received)
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Xmodem<T> {
+    /// Bounds how long an individual byte read may block, mirroring the
+    /// blocking [`Xmodem::set_read_timeout`]. The window is enforced with
+    /// [`tokio::time::timeout`], so it works for any async transport without a
+    /// `ReadTimeout` hook. Pass a new duration at any time.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.timeout = Some(timeout);
+    }
+
+    /// Returns the configured per-byte read timeout, if any.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Drives the packet loop for a transmission over the async transport,
+    /// mirroring the blocking `transmit_stream`. The block size follows the
+    /// `block_1k` opt-in.
+    async fn transmit_stream<R: AsyncRead + Unpin>(&mut self, mut data: R) -> Result<usize> {
+        let mut packet = [0u8; 1024];
+        let cap = if self.block_1k { 1024 } else { 128 };
+        let mut written = 0;
+        'next_packet: loop {
+            let n = read_max_async(&mut data, &mut packet[..cap]).await?;
+
+            if n == 0 {
+                self.write_packet(&[]).await?;
+                return Ok(written);
+            }
+
+            let block = if n > 128 { 1024 } else { 128 };
+            packet[n..block].iter_mut().for_each(|b| *b = 0);
+
+            loop {
+                match self.write_packet(&packet[..block]).await {
+                    Err(Error::ChecksumMismatch) => continue,
+                    Err(e) => return Err(e),
+                    Ok(_) => {
+                        written += n;
+                        continue 'next_packet;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a single byte from the inner transport, awaiting the transport.
+    /// If `abort_on_can` is `true`, [`Error::Canceled`] is returned for a
+    /// received `CAN`. A configured timeout surfaces as an `Io`/`TimedOut`
+    /// error, matching the blocking side so [`is_timeout`] recovery applies.
+    async fn read_byte(&mut self, abort_on_can: bool) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        match self.timeout {
+            Some(d) => match tokio::time::timeout(d, self.inner.read_exact(&mut buf)).await {
+                Err(_elapsed) => return Err(Error::Io(io::Error::new(io::ErrorKind::TimedOut, "read timed out"))),
+                Ok(result) => { result?; }
+            },
+            None => { self.inner.read_exact(&mut buf).await?; }
+        }
+
+        let byte = buf[0];
+        if abort_on_can && byte == CAN {
+            return Err(Error::Canceled);
+        }
+
+        Ok(byte)
+    }
+
+    /// Writes a single byte to the inner transport, awaiting the transport.
+    async fn write_byte(&mut self, byte: u8) -> Result<()> {
+        self.inner.write_all(&[byte]).await?;
+        Ok(())
+    }
+
+    /// Records a recoverable protocol error against the cumulative budget,
+    /// sending a `CAN` run once the budget is exhausted. See the blocking
+    /// `register_error` for details.
+    async fn register_error(&mut self) -> Result<bool> {
+        self.errors += 1;
+        if self.errors > self.max_errors {
+            self.cancel().await?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Signals the peer to abort the transfer by sending a run of `CAN` bytes.
+    async fn cancel(&mut self) -> Result<()> {
+        self.write_byte(CAN).await?;
+        self.write_byte(CAN).await
+    }
+
+    /// Reads a byte and compares it to `byte`, returning an error otherwise.
+    async fn expect_byte(&mut self, byte: u8, expected: &'static str) -> Result<u8> {
+        let b = self.read_byte(true).await?;
+        if byte == b {
+            Ok(b)
+        } else {
+            Err(Error::UnexpectedByte { expected, got: b })
+        }
+    }
+
+    /// Opens the receive handshake, returning the sender's first byte. In CRC
+    /// mode the `C` byte is retried before falling back to `NAK`; as with the
+    /// blocking [`Xmodem::read_start`], the fallback only fires when the read
+    /// times out, so CRC mode requires a timeout-bounded transport to be safe.
+    async fn read_start(&mut self) -> Result<u8> {
+        if self.checksum == Checksum::Crc16 {
+            for _ in 0..CRC_HANDSHAKE_RETRIES {
+                self.write_byte(CRC).await?;
+                match self.read_byte(true).await {
+                    Err(ref e) if is_timeout(e) => continue,
+                    other => return other,
+                }
+            }
+            self.checksum = Checksum::Standard;
+        }
+
+        self.write_byte(NAK).await?;
+        self.read_byte(true).await
+    }
+
+    /// Reads (downloads) a single packet over the async transport. See the
+    /// blocking [`Xmodem::read_packet`] for the protocol details, including the
+    /// read-timeout recovery that re-`NAK`s a stalled block.
+    pub async fn read_packet(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.read_packet_inner(buf).await {
+            Err(ref e) if is_timeout(e) => {
+                if self.register_error().await? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                // always a `NAK`; `C` is reserved for the opening handshake
+                self.write_byte(NAK).await?;
+                Err(Error::ChecksumMismatch)
+            }
+            other => other,
+        }
+    }
+
+    async fn read_packet_inner(&mut self, buf: &mut [u8]) -> Result<usize> {
+
+        let byte = if !self.started {
+            self.started = true;
+            self.errors = 0;
+            (self.progress)(Progress::Started);
+            self.read_start().await?
+        } else {
+            self.read_byte(true).await?
+        };
+
+        let block = match byte {
+            SOH => 128,
+            STX => 1024,
+            EOT => {
+                self.write_byte(NAK).await?;
+                self.expect_byte(EOT, "expected EOT byte").await?;
+                self.write_byte(ACK).await?;
+                return Ok(0);
+            }
+            _ => {
+                if self.register_error().await? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                return Err(Error::ChecksumMismatch);
+            }
+        };
+
+        if buf.len() < block {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "invalid packet format")));
+        }
+
+        let num = self.read_byte(true).await?;
+        let inv = self.read_byte(true).await?;
+        if num != self.packet || inv != !self.packet {
+            // a mismatched block number means a lost ACK or a desync: count it
+            // and NAK to prompt retransmission rather than reading stale payload
+            if self.register_error().await? {
+                return Err(Error::ExhaustedRetries);
+            }
+            self.write_byte(NAK).await?;
+            return Err(Error::ChecksumMismatch);
+        }
+
+        for i in 0..block {
+            buf[i] = self.read_byte(true).await?;
+        }
+
+        let ok = match self.checksum {
+            Checksum::Standard => {
+                let mut checksum = 0;
+                for i in 0..block {
+                    checksum = checksum.wrapping_add(buf[i]);
+                }
+                checksum == self.read_byte(true).await?
+            }
+            Checksum::Crc16 => {
+                let hi = self.read_byte(true).await?;
+                let lo = self.read_byte(true).await?;
+                crc16(&buf[..block]) == ((hi as u16) << 8 | lo as u16)
+            }
+        };
+
+        if !ok {
+            if self.register_error().await? {
+                return Err(Error::ExhaustedRetries);
+            }
+            self.write_byte(NAK).await?;
+            Err(Error::ChecksumMismatch)
+        } else {
+            (self.progress)(Progress::Packet(self.packet));
+            self.packet = self.packet.wrapping_add(1);
+            self.write_byte(ACK).await?;
+            Ok(block)
+        }
+    }
+
+    /// Sends (uploads) a single packet over the async transport. See the
+    /// blocking [`Xmodem::write_packet`] for the protocol details, including
+    /// the read-timeout recovery that re-sends a block on a stalled receiver.
+    pub async fn write_packet(&mut self, buf: &[u8]) -> Result<usize> {
+        match self.write_packet_inner(buf).await {
+            Err(ref e) if is_timeout(e) => {
+                if self.register_error().await? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                Err(Error::ChecksumMismatch)
+            }
+            other => other,
+        }
+    }
+
+    async fn write_packet_inner(&mut self, buf: &[u8]) -> Result<usize> {
+
+        let marker = match buf.len() {
+            0 => SOH, // unused: the empty-buffer branch sends `EOT` below
+            128 => SOH,
+            1024 => STX,
+            _ => return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected packet format"))),
+        };
+
+        if !self.started {
+            (self.progress)(Progress::Waiting);
+            match self.read_byte(true).await? {
+                CRC => self.checksum = Checksum::Crc16,
+                NAK => self.checksum = Checksum::Standard,
+                got => return Err(Error::UnexpectedByte { expected: "NAK or C as first byte", got }),
+            }
+            self.started = true;
+            self.errors = 0;
+            (self.progress)(Progress::Started);
+        }
+
+        if buf.len() != 0 {
+            let packet = self.packet;
+
+            self.write_byte(marker).await?;
+            self.write_byte(packet).await?;
+            self.read_byte(true).await?;
+            self.write_byte(!packet).await?;
+            self.read_byte(true).await?;
+
+            (self.progress)(Progress::Started);
+            for i in 0..buf.len() {
+                self.write_byte(buf[i]).await?;
+            }
+            match self.checksum {
+                Checksum::Standard => {
+                    let mut checksum: u8 = 0;
+                    for i in 0..buf.len() {
+                        checksum = checksum.wrapping_add(buf[i]);
+                    }
+                    self.write_byte(checksum).await?;
+                }
+                Checksum::Crc16 => {
+                    let crc = crc16(buf);
+                    self.write_byte((crc >> 8) as u8).await?;
+                    self.write_byte((crc & 0xff) as u8).await?;
+                }
+            }
+
+            let done = self.read_byte(true).await?;
+            match done {
+                ACK => {
+                    (self.progress)(Progress::Packet(self.packet));
+                    self.packet = self.packet.wrapping_add(1);
+                    Ok(buf.len())
+                }
+                NAK => {
+                    if self.register_error().await? {
+                        Err(Error::ExhaustedRetries)
+                    } else {
+                        Err(Error::ChecksumMismatch)
+                    }
+                }
+                got => Err(Error::UnexpectedByte { expected: "ACK or NAK", got }),
+            }
+        } else {
+            self.write_byte(EOT).await?;
+            self.expect_byte(NAK, "expected NAK to end the transmission").await?;
+            self.write_byte(EOT).await?;
+            self.expect_byte(ACK, "expected ACK to end the transmission").await?;
+            self.started = false;
+            Ok(0)
+        }
+    }
+
+    /// Flush the inner transport, awaiting completion.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.inner.flush().await?;
+        Ok(())
+    }
+}
+
+/// Reads from `data` until `buf` is full or EOF, returning the byte count.
+///
+/// This is the async counterpart of the `ReadExt::read_max` helper used by the
+/// blocking transmitter.
+async fn read_max_async<R>(data: &mut R, buf: &mut [u8]) -> io::Result<usize>
+    where R: AsyncRead + Unpin
+{
+    let mut filled = 0;
+    while filled < buf.len() {
+        match data.read(&mut buf[filled..]).await? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
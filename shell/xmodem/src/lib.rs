@@ -1,27 +1,154 @@
 use std::io;
+use std::time::Duration;
 
 #[cfg(test)] mod tests;
 mod read_ext;
 mod progress;
+#[cfg(feature = "tokio")] mod async_io;
 
 pub use progress::{Progress, ProgressFn};
 
 use read_ext::ReadExt;
 
 const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
 const EOT: u8 = 0x04;
 const ACK: u8 = 0x06;
 const NAK: u8 = 0x15;
 const CAN: u8 = 0x18;
+const CRC: u8 = 0x43; // 'C', requests CRC-16 mode
+
+/// Number of times the receiver retries the `C` byte before falling back to
+/// the `NAK` (checksum) handshake.
+const CRC_HANDSHAKE_RETRIES: usize = 3;
+
+/// Default cumulative error budget for a transfer.
+const DEFAULT_MAX_ERRORS: usize = 10;
+
+/// Integrity check appended to each packet (128-byte `SOH` or 1024-byte `STX`).
+///
+/// The receiver picks the mode at the start of a transfer: `Crc16` by sending
+/// the `C` byte, `Standard` by sending `NAK`. The sender mirrors whatever the
+/// receiver requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Checksum {
+    /// The original 8-bit additive checksum, negotiated with `NAK`.
+    Standard,
+    /// CRC-16/XMODEM (CCITT), negotiated with the `C` byte.
+    ///
+    /// On the receive side this mode only falls back to [`Standard`] when the
+    /// sender ignores the `C` request, which requires a read timeout to be set
+    /// (see [`Xmodem::set_read_timeout`]); with no timeout a CRC-mode receiver
+    /// talking to a checksum-only sender blocks forever.
+    ///
+    /// [`Standard`]: Checksum::Standard
+    Crc16,
+}
+
+/// Computes the CRC-16/XMODEM (CCITT) check value over `buf`.
+///
+/// Polynomial `0x1021`, initial value `0x0000`, no input/output reflection and
+/// no final XOR, processed byte-by-byte.
+fn crc16(buf: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in buf {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// Errors that can occur while running the XMODEM protocol.
+#[derive(Debug)]
+pub enum Error {
+    /// An underlying I/O read or write failed.
+    Io(io::Error),
+    /// The peer sent a `CAN` byte, cancelling the transfer.
+    Canceled,
+    /// A packet's checksum or CRC did not match the received data.
+    ChecksumMismatch,
+    /// The peer sent a byte the protocol did not expect at this point.
+    UnexpectedByte { expected: &'static str, got: u8 },
+    /// The cumulative error budget was exhausted before the transfer finished.
+    ExhaustedRetries,
+}
+
+/// A specialized [`Result`] type for XMODEM operations.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Canceled => write!(f, "transfer canceled by peer"),
+            Error::ChecksumMismatch => write!(f, "packet checksum mismatch"),
+            Error::UnexpectedByte { expected, got } => {
+                write!(f, "unexpected byte {:#04x}, expected {}", got, expected)
+            }
+            Error::ExhaustedRetries => write!(f, "error budget exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 /// Implementation of the XMODEM protocol.
 pub struct Xmodem<R> {
     packet: u8, //packet number
     inner: R,
     started: bool,
+    checksum: Checksum,
+    max_errors: usize,
+    errors: usize,
+    block_1k: bool, //opt into 1024-byte STX blocks when transmitting
+    timeout: Option<Duration>,
     progress: ProgressFn
 }
 
+/// Transports whose per-read blocking time can be bounded.
+///
+/// XMODEM needs to wake up when a peer stalls mid-packet so it can re-issue a
+/// `NAK`/`C` (receive) or re-send the last block (send) instead of hanging.
+/// The inner reader/writer is generic, so this hook lets [`Xmodem`] apply the
+/// [`set_read_timeout`](Xmodem::set_read_timeout) window to whatever transport
+/// it wraps — a socket, a serial port, and so on.
+pub trait ReadTimeout {
+    /// Sets the timeout applied to subsequent reads, or clears it with `None`.
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl ReadTimeout for std::net::TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        std::net::TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Returns `true` if `e` is the recoverable "read did not complete in time"
+/// error surfaced by a transport whose read timeout elapsed.
+fn is_timeout(e: &Error) -> bool {
+    matches!(e, Error::Io(io_err)
+        if matches!(io_err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut))
+}
+
 impl Xmodem<()> {
     /// Transmits `data` to the receiver `to` using the XMODEM protocol. If the
     /// length of the total data yielded by `data` is not a multiple of 128
@@ -29,7 +156,7 @@ impl Xmodem<()> {
     ///
     /// Returns the number of bytes written to `to`, excluding padding zeroes.
     #[inline]
-    pub fn transmit<R, W>(data: R, to: W) -> io::Result<usize>
+    pub fn transmit<R, W>(data: R, to: W) -> Result<usize>
         where W: io::Read + io::Write, R: io::Read
     {
         Xmodem::transmit_with_progress(data, to, progress::noop)
@@ -43,71 +170,67 @@ impl Xmodem<()> {
     /// the transmission. See the [`Progress`] enum for more information.
     ///
     /// Returns the number of bytes written to `to`, excluding padding zeroes.
-    pub fn transmit_with_progress<R, W>(mut data: R, to: W, f: ProgressFn) -> io::Result<usize>
+    pub fn transmit_with_progress<R, W>(data: R, to: W, f: ProgressFn) -> Result<usize>
         where W: io::Read + io::Write, R: io::Read
     {
-        let mut transmitter = Xmodem::new_with_progress(to, f);
-        let mut packet = [0u8; 128];
-        let mut written = 0;
-        'next_packet: loop {
-            let n = data.read_max(&mut packet)?;
-            packet[n..].iter_mut().for_each(|b| *b = 0);
-
-            if n == 0 {
-                transmitter.write_packet(&[])?;
-                return Ok(written);
-            }
+        Xmodem::new_with_progress(to, f).transmit_stream(data)
+    }
 
-            for _ in 0..10 {
-                match transmitter.write_packet(&packet) {
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
-                    Err(e) => return Err(e),
-                    Ok(_) => {
-                        written += n;
-                        continue 'next_packet;
-                    }
-                }
-            }
+    /// Transmits `data` using XMODEM-1K: each run of more than 128 bytes is
+    /// sent as a zero-padded 1024-byte `STX` block, and a final run of 128
+    /// bytes or fewer falls back to a zero-padded 128-byte `SOH` block.
+    /// The receiver must understand `STX` blocks.
+    #[inline]
+    pub fn transmit_1k<R, W>(data: R, to: W) -> Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        Xmodem::transmit_1k_with_progress(data, to, progress::noop)
+    }
 
-            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "bad transmit"));
-        }
+    /// As [`transmit_1k`](Xmodem::transmit_1k), reporting progress through `f`.
+    pub fn transmit_1k_with_progress<R, W>(data: R, to: W, f: ProgressFn) -> Result<usize>
+        where W: io::Read + io::Write, R: io::Read
+    {
+        let mut transmitter = Xmodem::new_with_progress(to, f);
+        transmitter.set_block_1k(true);
+        transmitter.transmit_stream(data)
     }
 
     /// Receives `data` from `from` using the XMODEM protocol and writes it into
-    /// `into`. Returns the number of bytes read from `from`, a multiple of 128.
+    /// `into`. Returns the total number of payload bytes written to `into`,
+    /// summed over each received block (128 bytes for `SOH`, 1024 for `STX`).
     #[inline]
-    pub fn receive<R, W>(from: R, into: W) -> io::Result<usize>
+    pub fn receive<R, W>(from: R, into: W) -> Result<usize>
        where R: io::Read + io::Write, W: io::Write
     {
         Xmodem::receive_with_progress(from, into, progress::noop)
     }
 
     /// Receives `data` from `from` using the XMODEM protocol and writes it into
-    /// `into`. Returns the number of bytes read from `from`, a multiple of 128.
+    /// `into`. Returns the total number of payload bytes written to `into`,
+    /// summed over each received block (128 bytes for `SOH`, 1024 for `STX`).
     ///
     /// The function `f` is used as a callback to indicate progress throughout
     /// the reception. See the [`Progress`] enum for more information.
-    pub fn receive_with_progress<R, W>(from: R, mut into: W, f: ProgressFn) -> io::Result<usize>
+    pub fn receive_with_progress<R, W>(from: R, mut into: W, f: ProgressFn) -> Result<usize>
        where R: io::Read + io::Write, W: io::Write
     {
         let mut receiver = Xmodem::new_with_progress(from, f);
-        let mut packet = [0u8; 128];
+        let mut packet = [0u8; 1024];
         let mut received = 0;
         'next_packet: loop {
-            for _ in 0..10 {
+            loop {
                 match receiver.read_packet(&mut packet) {
-                    Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(Error::ChecksumMismatch) => continue,
                     Err(e) => return Err(e),
                     Ok(0) => break 'next_packet,
                     Ok(n) => {
                         received += n;
-                        into.write_all(&packet)?;
+                        into.write_all(&packet[..n])?;
                         continue 'next_packet;
                     }
                 }
             }
-
-            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "bad receive"));
         }
 
         Ok(received)
@@ -119,7 +242,42 @@ impl<T: io::Read + io::Write> Xmodem<T> {
     /// `inner`. The returned instance can be used for both receiving
     /// (downloading) and sending (uploading).
     pub fn new(inner: T) -> Self {
-        Xmodem { packet: 1, started: false, inner, progress: progress::noop}
+        Xmodem { packet: 1, started: false, checksum: Checksum::Standard, max_errors: DEFAULT_MAX_ERRORS, errors: 0, block_1k: false, timeout: None, inner, progress: progress::noop}
+    }
+
+    /// Forces the integrity check mode used for this transfer.
+    ///
+    /// On the receive side this decides whether the handshake opens with the
+    /// `C` byte (`Crc16`) or `NAK` (`Standard`); on the send side the mode is
+    /// overridden by whatever the receiver actually requests.
+    pub fn set_checksum(&mut self, checksum: Checksum) {
+        self.checksum = checksum;
+    }
+
+    /// Sets the cumulative error budget for the transfer.
+    ///
+    /// Checksum/CRC failures and garbage leading bytes each count once against
+    /// this budget; when it is exhausted a run of `CAN` bytes is sent to the
+    /// peer and the transfer fails. The count is reset when a transfer starts.
+    pub fn set_max_errors(&mut self, max_errors: usize) {
+        self.max_errors = max_errors;
+    }
+
+    /// Opts into XMODEM-1K for the crate's packet loop: runs of more than 128
+    /// bytes are sent as zero-padded 1024-byte `STX` blocks, and a final run
+    /// of 128 bytes or fewer as a zero-padded 128-byte `SOH` block. Off by
+    /// default, so [`transmit`](Xmodem::transmit) stays classic 128-byte
+    /// XMODEM that a 128-only receiver can parse. That loop runs only on the
+    /// instance that `transmit`/[`transmit_1k`](Xmodem::transmit_1k) create
+    /// themselves (`transmit_1k` sets this flag on it). `write_packet` sizes
+    /// the block from the buffer length and ignores this flag.
+    pub fn set_block_1k(&mut self, block_1k: bool) {
+        self.block_1k = block_1k;
+    }
+
+    /// Returns the configured per-byte read timeout, if any.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.timeout
     }
 
     /// Returns a new `Xmodem` instance with the internal reader/writer set to
@@ -128,24 +286,58 @@ impl<T: io::Read + io::Write> Xmodem<T> {
     /// callback to indicate progress throughout the transfer. See the
     /// [`Progress`] enum for more information.
     pub fn new_with_progress(inner: T, f: ProgressFn) -> Self {
-        Xmodem { packet: 1, started: false, inner, progress: f }
+        Xmodem { packet: 1, started: false, checksum: Checksum::Standard, max_errors: DEFAULT_MAX_ERRORS, errors: 0, block_1k: false, timeout: None, inner, progress: f }
+    }
+
+    /// Drives the packet loop for a transmission, reading from `data` and
+    /// sending blocks until it is exhausted. The block size follows
+    /// [`block_1k`](Xmodem::set_block_1k): when opted in, a run of more than
+    /// 128 bytes goes out as a zero-padded 1024-byte block; otherwise (and for
+    /// a run of 128 bytes or fewer) as a zero-padded 128-byte block.
+    fn transmit_stream<R: io::Read>(&mut self, mut data: R) -> Result<usize> {
+        let mut packet = [0u8; 1024];
+        // a 128-only transfer reads at most a block at a time, so a full run
+        // never overruns the `SOH` block it will be padded into
+        let cap = if self.block_1k { 1024 } else { 128 };
+        let mut written = 0;
+        'next_packet: loop {
+            let n = data.read_max(&mut packet[..cap])?;
+
+            if n == 0 {
+                self.write_packet(&[])?;
+                return Ok(written);
+            }
+
+            let block = if n > 128 { 1024 } else { 128 };
+            packet[n..block].iter_mut().for_each(|b| *b = 0);
+
+            loop {
+                match self.write_packet(&packet[..block]) {
+                    Err(Error::ChecksumMismatch) => continue,
+                    Err(e) => return Err(e),
+                    Ok(_) => {
+                        written += n;
+                        continue 'next_packet;
+                    }
+                }
+            }
+        }
     }
 
     /// Reads a single byte from the inner I/O stream. If `abort_on_can` is
-    /// `true`, an error of `ConnectionAborted` is returned if the read byte is
-    /// `CAN`.
+    /// `true`, [`Error::Canceled`] is returned if the read byte is `CAN`.
     ///
     /// # Errors
     ///
     /// Returns an error if reading from the inner stream fails or if
     /// `abort_on_can` is `true` and the read byte is `CAN`.
-    fn read_byte(&mut self, abort_on_can: bool) -> io::Result<u8> {
+    fn read_byte(&mut self, abort_on_can: bool) -> Result<u8> {
         let mut buf = [0u8; 1];
         self.inner.read_exact(&mut buf)?;
 
         let byte = buf[0];
         if abort_on_can && byte == CAN {
-            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "received CAN"));
+            return Err(Error::Canceled);
         }
 
         Ok(byte)
@@ -156,64 +348,55 @@ impl<T: io::Read + io::Write> Xmodem<T> {
     /// # Errors
     ///
     /// Returns an error if writing to the inner stream fails.
-    fn write_byte(&mut self, byte: u8) -> io::Result<()> {
-        self.inner.write_all(&[byte])
+    fn write_byte(&mut self, byte: u8) -> Result<()> {
+        self.inner.write_all(&[byte])?;
+        Ok(())
     }
 
-    /// Reads a single byte from the inner I/O stream and compares it to `byte`.
-    /// If the bytes match, the byte is returned as an `Ok`. If they differ and
-    /// the read byte is not `CAN`, an error of `InvalidData` with the message
-    /// `expected` is returned. If they differ and the read byte is `CAN`, an
-    /// error of `ConnectionAborted` is returned. In either case, if they
-    /// differ, a `CAN` byte is written out to the inner stream.
+    /// Records a recoverable protocol error against the cumulative budget.
     ///
-    /// # Errors
-    ///
-    /// Returns an error if reading from the inner stream fails, if the read
-    /// byte was not `byte`, if the read byte was `CAN` and `byte` is not `CAN`,
-    /// or if writing the `CAN` byte failed on byte mismatch.
-    fn expect_byte_or_cancel(&mut self, byte: u8, msg: &'static str) -> io::Result<u8> {
-        
-        let result = Xmodem::read_byte(self, false)?;
-
-        if byte == result { 
-            Ok(byte) 
-        } 
-        else if result == CAN { 
-            Xmodem::write_byte(self, CAN)?; //self.read_byte(CAN);
-            Err(io::Error::new(io::ErrorKind::ConnectionAborted, "received CAN")) 
-        }
-        else { 
-            Xmodem::write_byte(self, CAN)?; 
-            Err(io::Error::new(io::ErrorKind::InvalidData, msg)) 
+    /// Returns `true` once the budget is exhausted, after signalling the peer
+    /// with a `CAN` run so it aborts cleanly instead of hanging.
+    fn register_error(&mut self) -> Result<bool> {
+        self.errors += 1;
+        if self.errors > self.max_errors {
+            self.cancel()?;
+            Ok(true)
+        } else {
+            Ok(false)
         }
     }
 
+    /// Signals the peer to abort the transfer by sending a run of `CAN` bytes.
+    fn cancel(&mut self) -> Result<()> {
+        self.write_byte(CAN)?;
+        self.write_byte(CAN)
+    }
+
     /// Reads a single byte from the inner I/O stream and compares it to `byte`.
-    /// If they differ, an error of `InvalidData` with the message `expected` is
-    /// returned. Otherwise the byte is returned. If `byte` is not `CAN` and the
-    /// read byte is `CAN`, a `ConnectionAborted` error is returned.
+    /// If they differ, [`Error::UnexpectedByte`] carrying `expected` is
+    /// returned. Otherwise the byte is returned. If the read byte is `CAN`,
+    /// [`Error::Canceled`] is returned (the underlying read aborts on `CAN`).
     ///
     /// # Errors
     ///
-    /// Returns an error if reading from the inner stream fails, or if the read
-    /// byte was not `byte`. If the read byte differed and was `CAN`, an error
-    /// of `ConnectionAborted` is returned. Otherwise, the error kind is
-    /// `InvalidData`.
-    fn expect_byte(&mut self, byte: u8, expected: &'static str) -> io::Result<u8> {
+    /// Returns an error if reading from the inner stream fails, if the read
+    /// byte was not `byte`, or if the read byte was `CAN`.
+    fn expect_byte(&mut self, byte: u8, expected: &'static str) -> Result<u8> {
 
         let result = Xmodem::read_byte(self, true);
         match result {
-            Ok(b) => 
-                if byte == b { Ok(b) } 
-                else if b == CAN { Err(io::Error::new(io::ErrorKind::ConnectionAborted, "received CAN")) }
-                else { Err(io::Error::new(io::ErrorKind::InvalidData, expected)) },
+            Ok(b) =>
+                if byte == b { Ok(b) }
+                else { Err(Error::UnexpectedByte { expected, got: b }) },
             Err(e) => Err(e)
         }
     }
 
     /// Reads (downloads) a single packet from the inner stream using the XMODEM
-    /// protocol. On success, returns the number of bytes read (always 128).
+    /// protocol. On success, returns the number of bytes read: 128 for an
+    /// `SOH` block, 1024 for an `STX` (XMODEM-1K) block, or 0 at end of
+    /// transmission.
     ///
     /// The progress callback is called with `Progress::Start` when reception
     /// for the first packet has started and subsequently with
@@ -221,67 +404,140 @@ impl<T: io::Read + io::Write> Xmodem<T> {
     ///
     /// # Errors
     ///
-    /// Returns an error if reading or writing to the inner stream fails at any
-    /// point. Also returns an error if the XMODEM protocol indicates an error.
-    /// In particular, an `InvalidData` error is returned when:
+    /// Returns [`Error::Io`] if reading or writing to the inner stream fails at
+    /// any point. [`Error::UnexpectedByte`] is returned when the sender's
+    /// second `EOT` does not arrive. [`Error::ChecksumMismatch`] is returned
+    /// when a packet fails its checksum or the leading byte is garbage, and
+    /// [`Error::ExhaustedRetries`] once the error budget is used up.
+    /// [`Error::Canceled`] is returned if a `CAN` byte is received.
     ///
-    ///   * The sender's first byte for a packet isn't `EOT` or `SOH`.
-    ///   * The sender doesn't send a second `EOT` after the first.
-    ///   * The received packet numbers don't match the expected values.
+    /// If a read timeout is configured (see [`set_read_timeout`]) and a byte
+    /// does not arrive in time, the current block is `NAK`'d (in CRC mode too;
+    /// `C` is only re-sent during the opening handshake) to prompt
+    /// retransmission and the stall counts against the error budget,
+    /// surfacing as [`Error::ChecksumMismatch`] so the caller retries.
     ///
-    /// An error of kind `Interrupted` is returned if a packet checksum fails.
-    ///
-    /// An error of kind `ConnectionAborted` is returned if a `CAN` byte is
-    /// received when not expected.
-    ///
-    /// An error of kind `UnexpectedEof` is returned if `buf.len() < 128`.
-    pub fn read_packet(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-
-        if buf.len() < 128 {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "invalid packet format"));
+    /// [`set_read_timeout`]: Xmodem::set_read_timeout
+    pub fn read_packet(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.read_packet_inner(buf) {
+            Err(ref e) if is_timeout(e) => {
+                if self.register_error()? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                // prompt the sender to retransmit the current block; always a
+                // `NAK` — `C` is reserved for opening the handshake, and the
+                // sender's mid-transfer resend path only recognises `NAK`
+                self.write_byte(NAK)?;
+                Err(Error::ChecksumMismatch)
+            }
+            other => other,
         }
-        
-        if !self.started {
-            self.write_byte(NAK)?;
+    }
+
+    fn read_packet_inner(&mut self, buf: &mut [u8]) -> Result<usize> {
+
+        let byte = if !self.started {
             self.started = true;
+            self.errors = 0;
             (self.progress)(Progress::Started);
-        }
-
-        let byte = self.read_byte(true)?;
-        if byte == SOH {
-            if self.read_byte(true)? != self.packet {
-                self.write_byte(CAN)?;
+            self.read_start()?
+        } else {
+            self.read_byte(true)?
+        };
+
+        // the leading byte selects the block size (or ends the transfer)
+        let block = match byte {
+            SOH => 128,
+            STX => 1024,
+            EOT => {
+                self.write_byte(NAK)?;
+                self.expect_byte(EOT, "expected EOT byte")?;
+                self.write_byte(ACK)?;
+                return Ok(0);
             }
-            if self.read_byte(true)? != !self.packet {
-                self.write_byte(CAN)?;
+            _ => {
+                // a desynchronised leading byte is counted against the budget;
+                // no `NAK` is sent, and the next call reads the following byte
+                if self.register_error()? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                return Err(Error::ChecksumMismatch);
             }
+        };
+
+        if buf.len() < block {
+            return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "invalid packet format")));
         }
-        else if byte == EOT {
+
+        let num = self.read_byte(true)?;
+        let inv = self.read_byte(true)?;
+        if num != self.packet || inv != !self.packet {
+            // a mismatched block number means a lost ACK or a desync: count it
+            // and NAK to prompt retransmission rather than reading stale payload
+            if self.register_error()? {
+                return Err(Error::ExhaustedRetries);
+            }
             self.write_byte(NAK)?;
-            self.expect_byte(EOT, "expected EOT byte")?;
-            self.write_byte(ACK)?;
-            return Ok(0);
-        }
-        else {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "expected SOH or EOT byte"));
+            return Err(Error::ChecksumMismatch);
         }
 
-        let mut checksum = 0;
-        for i in 0..127 {
+        for i in 0..block {
             buf[i] = self.read_byte(true)?;
-            checksum = (checksum + buf[i]) % 256;
         }
 
-        if checksum != self.read_byte(true)? {
+        let ok = match self.checksum {
+            Checksum::Standard => {
+                let mut checksum = 0;
+                for i in 0..block {
+                    checksum = checksum.wrapping_add(buf[i]);
+                }
+                checksum == self.read_byte(true)?
+            }
+            Checksum::Crc16 => {
+                let hi = self.read_byte(true)?;
+                let lo = self.read_byte(true)?;
+                crc16(&buf[..block]) == ((hi as u16) << 8 | lo as u16)
+            }
+        };
+
+        if !ok {
+            if self.register_error()? {
+                return Err(Error::ExhaustedRetries);
+            }
             self.write_byte(NAK)?;
-            return Err(io::Error::new(io::ErrorKind::Interrupted, "checksum failed"));
+            return Err(Error::ChecksumMismatch);
         } else {
             (self.progress)(Progress::Packet(self.packet));
             self.packet = self.packet.wrapping_add(1);
             self.write_byte(ACK)?;
-            return Ok(128);
+            return Ok(block);
+        }
+
+    }
+
+    /// Opens the receive handshake, returning the sender's first byte.
+    ///
+    /// In CRC mode the `C` byte is sent up to [`CRC_HANDSHAKE_RETRIES`] times;
+    /// if the sender never answers, the transfer falls back to the `NAK`
+    /// (checksum) handshake. Detecting "never answers" relies on the read
+    /// timeout: each `C` is retried only when the read times out. Without a
+    /// timeout configured via [`set_read_timeout`](Xmodem::set_read_timeout),
+    /// a `C` sent to a checksum-only sender blocks forever and the fallback
+    /// can never fire — so CRC mode requires a read timeout to be safe.
+    fn read_start(&mut self) -> Result<u8> {
+        if self.checksum == Checksum::Crc16 {
+            for _ in 0..CRC_HANDSHAKE_RETRIES {
+                self.write_byte(CRC)?;
+                match self.read_byte(true) {
+                    Err(ref e) if is_timeout(e) => continue,
+                    other => return other,
+                }
+            }
+            self.checksum = Checksum::Standard;
         }
 
+        self.write_byte(NAK)?;
+        self.read_byte(true)
     }
 
 
@@ -298,35 +554,57 @@ impl<T: io::Read + io::Write> Xmodem<T> {
     ///
     /// # Errors
     ///
-    /// Returns an error if reading or writing to the inner stream fails at any
-    /// point. Also returns an error if the XMODEM protocol indicates an error.
-    /// In particular, an `InvalidData` error is returned when:
-    ///
-    ///   * The receiver's first byte isn't a `NAK`.
-    ///   * The receiver doesn't respond with a `NAK` to the first `EOT`.
-    ///   * The receiver doesn't respond with an `ACK` to the second `EOT`.
-    ///   * The receiver responds to a complete packet with something besides
-    ///     `ACK` or `NAK`.
+    /// Returns [`Error::Io`] if reading or writing to the inner stream fails.
+    /// [`Error::UnexpectedByte`] is returned when the receiver's first byte is
+    /// not `NAK`/`C`, when it does not answer the `EOT` handshake correctly, or
+    /// when it replies to a packet with something other than `ACK`/`NAK`.
+    /// [`Error::ChecksumMismatch`] is returned on a `NAK` response and
+    /// [`Error::ExhaustedRetries`] once the error budget is used up.
+    /// [`Error::Canceled`] is returned if a `CAN` byte is received at any point.
     ///
-    /// An error of kind `UnexpectedEof` is returned if `buf.len() < 128 &&
-    /// buf.len() != 0`.
+    /// An [`Error::Io`] of kind `UnexpectedEof` is returned if `buf.len()` is
+    /// not one of `0`, `128` (an `SOH` block) or `1024` (an XMODEM-1K block).
     ///
-    /// An error of kind `ConnectionAborted` is returned if a `CAN` byte is
-    /// received when not expected.
+    /// If a read timeout is configured (see [`set_read_timeout`]) and the
+    /// receiver stalls — including while waiting for the initial handshake — the
+    /// stall counts against the error budget and surfaces as
+    /// [`Error::ChecksumMismatch`], prompting the caller to re-send the block.
     ///
-    /// An error of kind `Interrupted` is returned if a packet checksum fails.
-    pub fn write_packet(&mut self, buf: &[u8]) -> io::Result<usize> {
-
-        // if packet is less than 128 bytes and is not empty (=> EOT)
-        if buf.len() < 128 && buf.len() != 0 {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected packet format"));
+    /// [`set_read_timeout`]: Xmodem::set_read_timeout
+    pub fn write_packet(&mut self, buf: &[u8]) -> Result<usize> {
+        match self.write_packet_inner(buf) {
+            Err(ref e) if is_timeout(e) => {
+                if self.register_error()? {
+                    return Err(Error::ExhaustedRetries);
+                }
+                Err(Error::ChecksumMismatch)
+            }
+            other => other,
         }
+    }
+
+    fn write_packet_inner(&mut self, buf: &[u8]) -> Result<usize> {
+
+        // the block size selects the leading marker: `SOH` for 128 bytes,
+        // `STX` for a 1024-byte XMODEM-1K block (`0` is the end-of-transmission)
+        let marker = match buf.len() {
+            0 => SOH, // unused: the empty-buffer branch sends `EOT` below
+            128 => SOH,
+            1024 => STX,
+            _ => return Err(Error::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected packet format"))),
+        };
 
         // if this is the first call to `write_packet`, ensure the transmission is started properly
         if !self.started {
             (self.progress)(Progress::Waiting);
-            self.expect_byte(NAK, "expected NAK as first byte")?;
+            // the receiver picks the mode: `C` for CRC-16, `NAK` for checksum
+            match self.read_byte(true)? {
+                CRC => self.checksum = Checksum::Crc16,
+                NAK => self.checksum = Checksum::Standard,
+                got => return Err(Error::UnexpectedByte { expected: "NAK or C as first byte", got }),
+            }
             self.started = true;
+            self.errors = 0;
             (self.progress)(Progress::Started);
         }
 
@@ -335,20 +613,31 @@ impl<T: io::Read + io::Write> Xmodem<T> {
             let packet = self.packet; //because self is mutably borrowed later
 
             // as per the XMODEM protocol specifications
-            self.write_byte(SOH)?;
+            self.write_byte(marker)?;
             self.write_byte(packet)?;
             self.read_byte(true)?;
             self.write_byte(!packet)?;
             self.read_byte(true)?;
 
-            // send the payload and compute/send the checksum
+            // send the payload and the matching trailer
             (self.progress)(Progress::Started);
-            let mut checksum: u8 = 0;
-            for i in 0..127 {
+            for i in 0..buf.len() {
                 self.write_byte(buf[i])?;
-                checksum = (checksum + buf[i]) % 256;
             }
-            self.write_byte(checksum);
+            match self.checksum {
+                Checksum::Standard => {
+                    let mut checksum: u8 = 0;
+                    for i in 0..buf.len() {
+                        checksum = checksum.wrapping_add(buf[i]);
+                    }
+                    self.write_byte(checksum)?;
+                }
+                Checksum::Crc16 => {
+                    let crc = crc16(buf);
+                    self.write_byte((crc >> 8) as u8)?;
+                    self.write_byte((crc & 0xff) as u8)?;
+                }
+            }
 
             // check whether the payload was successfully sent or not
             let done = self.read_byte(true)?;
@@ -358,8 +647,14 @@ impl<T: io::Read + io::Write> Xmodem<T> {
                     self.packet = self.packet.wrapping_add(1);
                     Ok(buf.len())
                 }
-                NAK => Err(io::Error::new(io::ErrorKind::Interrupted, "checksum failed")),
-                _ => Err(io::Error::new(io::ErrorKind::InvalidData, "expected ACK or NAK")),
+                NAK => {
+                    if self.register_error()? {
+                        Err(Error::ExhaustedRetries)
+                    } else {
+                        Err(Error::ChecksumMismatch)
+                    }
+                }
+                got => Err(Error::UnexpectedByte { expected: "ACK or NAK", got }),
             }
         } 
         // end the transmission with 2 handshakes
@@ -385,3 +680,22 @@ impl<T: io::Read + io::Write> Xmodem<T> {
         self.inner.flush()
     }
 }
+
+impl<T: io::Read + io::Write + ReadTimeout> Xmodem<T> {
+    /// Bounds how long an individual byte read may block.
+    ///
+    /// The window is applied to the inner transport through the [`ReadTimeout`]
+    /// hook, so a peer that stops transmitting mid-packet no longer wedges the
+    /// transfer: the read wakes up, the stall counts against the error budget,
+    /// and the current block is retransmitted (or the handshake retried). Pass
+    /// a new duration at any time; it takes effect on the next read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inner transport rejects the timeout.
+    pub fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.inner.set_read_timeout(Some(timeout))?;
+        self.timeout = Some(timeout);
+        Ok(())
+    }
+}
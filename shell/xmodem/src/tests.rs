@@ -0,0 +1,195 @@
+//! Unit tests for the XMODEM implementation.
+//!
+//! Driving a full transfer needs an inner stream that can be both read from
+//! and written to, so the tests talk to a [`Duplex`] backed by an in-memory
+//! script of receiver (or sender) responses. This keeps every case
+//! deterministic and single-threaded while still exercising the real packet
+//! framing, checksum/CRC trailers and the error-budget bookkeeping.
+
+use std::io::{self, Read, Write};
+
+use crate::{
+    crc16, Checksum, Error, Xmodem, ACK, CRC, EOT, NAK, SOH, STX,
+};
+
+/// A read/write stream whose reads are served from a fixed `input` script and
+/// whose writes are appended to `output`. Standing in for the peer, `input`
+/// holds exactly the bytes the peer would send in reply.
+struct Duplex {
+    input: io::Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Duplex {
+    fn new(input: Vec<u8>) -> Self {
+        Duplex { input: io::Cursor::new(input), output: Vec::new() }
+    }
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds a valid incoming packet (leading marker, block number pair, payload
+/// and the trailer for `checksum`) as the sender would frame it.
+fn frame(block_number: u8, payload: &[u8], checksum: Checksum) -> Vec<u8> {
+    let marker = if payload.len() == 1024 { STX } else { SOH };
+    let mut packet = vec![marker, block_number, !block_number];
+    packet.extend_from_slice(payload);
+    match checksum {
+        Checksum::Standard => {
+            let sum = payload.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+            packet.push(sum);
+        }
+        Checksum::Crc16 => {
+            let crc = crc16(payload);
+            packet.push((crc >> 8) as u8);
+            packet.push((crc & 0xff) as u8);
+        }
+    }
+    packet
+}
+
+#[test]
+fn crc16_matches_known_vectors() {
+    // the canonical CRC-16/XMODEM check value for the ASCII string "123456789"
+    assert_eq!(crc16(b"123456789"), 0x31c3);
+    assert_eq!(crc16(&[]), 0x0000);
+    assert_eq!(crc16(&[0x00]), 0x0000);
+    assert_eq!(crc16(&[0xff]), 0x1ef0);
+}
+
+#[test]
+fn read_packet_standard() {
+    let payload = [0x42u8; 128];
+    // read_start writes the opening NAK, so the script is just the packet
+    let script = frame(1, &payload, Checksum::Standard);
+    let mut xmodem = Xmodem::new(Duplex::new(script));
+
+    let mut buf = [0u8; 128];
+    assert_eq!(xmodem.read_packet(&mut buf).unwrap(), 128);
+    assert_eq!(&buf[..], &payload[..]);
+    // NAK to open the handshake, then ACK for the good block
+    assert_eq!(xmodem.inner.output, vec![NAK, ACK]);
+}
+
+#[test]
+fn read_packet_crc() {
+    let payload = [0x99u8; 128];
+    let script = frame(1, &payload, Checksum::Crc16);
+    let mut xmodem = Xmodem::new(Duplex::new(script));
+    xmodem.set_checksum(Checksum::Crc16);
+
+    let mut buf = [0u8; 128];
+    assert_eq!(xmodem.read_packet(&mut buf).unwrap(), 128);
+    assert_eq!(&buf[..], &payload[..]);
+    // CRC mode opens with `C` instead of `NAK`
+    assert_eq!(xmodem.inner.output, vec![CRC, ACK]);
+}
+
+#[test]
+fn read_packet_1k_block() {
+    let payload = [0x7eu8; 1024];
+    let script = frame(1, &payload, Checksum::Standard);
+    let mut xmodem = Xmodem::new(Duplex::new(script));
+
+    let mut buf = [0u8; 1024];
+    assert_eq!(xmodem.read_packet(&mut buf).unwrap(), 1024);
+    assert_eq!(&buf[..], &payload[..]);
+}
+
+#[test]
+fn read_packet_end_of_transmission() {
+    // first EOT ends the stream; the receiver NAKs, expects a second EOT, ACKs
+    let mut xmodem = Xmodem::new(Duplex::new(vec![EOT, EOT]));
+    let mut buf = [0u8; 128];
+    assert_eq!(xmodem.read_packet(&mut buf).unwrap(), 0);
+    assert_eq!(xmodem.inner.output, vec![NAK, NAK, ACK]);
+}
+
+#[test]
+fn read_packet_checksum_mismatch_naks() {
+    let payload = [0x01u8; 128];
+    let mut script = frame(1, &payload, Checksum::Standard);
+    *script.last_mut().unwrap() ^= 0xff; // corrupt the trailing checksum
+    let mut xmodem = Xmodem::new(Duplex::new(script));
+
+    let mut buf = [0u8; 128];
+    match xmodem.read_packet(&mut buf) {
+        Err(Error::ChecksumMismatch) => {}
+        other => panic!("expected ChecksumMismatch, got {:?}", other),
+    }
+    // a bad block is NAK'd after the opening NAK
+    assert_eq!(xmodem.inner.output, vec![NAK, NAK]);
+}
+
+#[test]
+fn read_packet_error_budget_exhausted() {
+    // a run of garbage leading bytes, one more than the budget allows
+    let mut xmodem = Xmodem::new(Duplex::new(vec![0xaa; 4]));
+    xmodem.set_max_errors(3);
+
+    let mut buf = [0u8; 128];
+    // the first three garbage bytes stay within budget
+    for _ in 0..3 {
+        assert!(matches!(xmodem.read_packet(&mut buf), Err(Error::ChecksumMismatch)));
+    }
+    // the fourth tips over the budget and aborts the transfer
+    match xmodem.read_packet(&mut buf) {
+        Err(Error::ExhaustedRetries) => {}
+        other => panic!("expected ExhaustedRetries, got {:?}", other),
+    }
+}
+
+#[test]
+fn write_packet_standard() {
+    // the receiver opens with NAK, answers each of the block-number pair
+    // (the sender reads one byte after each), then ACKs the packet
+    let mut xmodem = Xmodem::new(Duplex::new(vec![NAK, ACK, ACK, ACK]));
+    let payload = [0x42u8; 128];
+    assert_eq!(xmodem.write_packet(&payload).unwrap(), 128);
+
+    let out = &xmodem.inner.output;
+    assert_eq!(out[0], SOH);
+    assert_eq!(out[1], 1);
+    assert_eq!(out[2], !1);
+    assert_eq!(&out[3..3 + 128], &payload[..]);
+    let sum = payload.iter().fold(0u8, |acc, &b| acc.wrapping_add(b));
+    assert_eq!(out[3 + 128], sum);
+}
+
+#[test]
+fn write_packet_crc_1k_block() {
+    // `C` selects CRC-16 mode; the 1024-byte payload is framed with `STX`
+    let mut xmodem = Xmodem::new(Duplex::new(vec![CRC, ACK, ACK, ACK]));
+    let payload = [0x5au8; 1024];
+    assert_eq!(xmodem.write_packet(&payload).unwrap(), 1024);
+
+    let out = &xmodem.inner.output;
+    assert_eq!(out[0], STX);
+    let crc = crc16(&payload);
+    assert_eq!(out[3 + 1024], (crc >> 8) as u8);
+    assert_eq!(out[3 + 1024 + 1], (crc & 0xff) as u8);
+}
+
+#[test]
+fn write_packet_rejects_odd_sizes() {
+    let mut xmodem = Xmodem::new(Duplex::new(vec![]));
+    match xmodem.write_packet(&[0u8; 64]) {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof),
+        other => panic!("expected UnexpectedEof, got {:?}", other),
+    }
+}